@@ -0,0 +1,205 @@
+//! On-chain settlement gating: confirms the recovered signer can actually
+//! fund the payment it authorized, by querying ERC-20 balance/allowance or
+//! native balance through an `ethers` JSON-RPC provider.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use ethers::contract::abigen;
+use ethers::providers::{Http, Middleware, Provider, ProviderError};
+use ethers::types::{Address, Bytes, H256, U256};
+
+/// Per-call RPC timeout so a slow or unreachable node cannot stall a
+/// verification request.
+const RPC_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Return value `isValidSignature` must produce for a valid ERC-1271
+/// signature, per the standard.
+const ERC1271_MAGIC_VALUE: [u8; 4] = [0x16, 0x26, 0xba, 0x7e];
+
+abigen!(
+    IERC20,
+    r#"[
+        function balanceOf(address owner) external view returns (uint256)
+        function allowance(address owner, address spender) external view returns (uint256)
+    ]"#
+);
+
+abigen!(
+    IERC1271,
+    r#"[
+        function isValidSignature(bytes32 _hash, bytes memory _signature) public view returns (bytes4 magicValue)
+    ]"#
+);
+
+#[derive(Debug)]
+pub enum ChainError {
+    /// No RPC provider is configured for the requested chain id.
+    UnknownChain(u64),
+    /// The recovered address or token/spender field was not a valid address.
+    InvalidAddress(String),
+    /// The RPC call did not complete within `RPC_TIMEOUT`.
+    Timeout,
+    /// The underlying JSON-RPC call failed.
+    Rpc(ProviderError),
+}
+
+impl std::fmt::Display for ChainError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChainError::UnknownChain(chain_id) => {
+                write!(f, "no RPC provider configured for chainId {}", chain_id)
+            }
+            ChainError::InvalidAddress(addr) => write!(f, "invalid address: {}", addr),
+            ChainError::Timeout => write!(f, "on-chain check timed out"),
+            ChainError::Rpc(e) => write!(f, "RPC call failed: {}", e),
+        }
+    }
+}
+
+/// Result of checking a payer's on-chain funding against a requested amount.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SettlementCheck {
+    pub balance_sufficient: bool,
+    pub allowance_sufficient: bool,
+}
+
+/// Holds one `Provider<Http>` per chain id, configured via an RPC-URL map.
+pub struct ChainGateway {
+    providers: HashMap<u64, Arc<Provider<Http>>>,
+}
+
+impl ChainGateway {
+    /// Builds a gateway from `RPC_URL_MAP`, a comma-separated list of
+    /// `chainId=url` pairs, e.g. `RPC_URL_MAP="1=https://eth.llamarpc.com,137=https://polygon-rpc.com"`.
+    pub fn from_env() -> Self {
+        let mut providers = HashMap::new();
+        if let Ok(map) = std::env::var("RPC_URL_MAP") {
+            for entry in map.split(',').filter(|e| !e.trim().is_empty()) {
+                let Some((chain_id, url)) = entry.split_once('=') else {
+                    continue;
+                };
+                let (Ok(chain_id), Ok(provider)) = (
+                    chain_id.trim().parse::<u64>(),
+                    Provider::<Http>::try_from(url.trim()),
+                ) else {
+                    continue;
+                };
+                providers.insert(chain_id, Arc::new(provider));
+            }
+        }
+        Self { providers }
+    }
+
+    fn provider(&self, chain_id: u64) -> Result<&Arc<Provider<Http>>, ChainError> {
+        self.providers
+            .get(&chain_id)
+            .ok_or(ChainError::UnknownChain(chain_id))
+    }
+
+    /// Checks `holder`'s ERC-20 `balanceOf` and `allowance(holder, spender)`
+    /// against `amount`, issuing both `eth_call`s concurrently.
+    pub async fn check_erc20(
+        &self,
+        chain_id: u64,
+        token: &str,
+        holder: &str,
+        spender: &str,
+        amount: U256,
+    ) -> Result<SettlementCheck, ChainError> {
+        let provider = self.provider(chain_id)?;
+        let token = parse_address(token)?;
+        let holder = parse_address(holder)?;
+        let spender = parse_address(spender)?;
+
+        let contract = IERC20::new(token, Arc::clone(provider));
+        let call = async {
+            tokio::try_join!(
+                contract.balance_of(holder).call(),
+                contract.allowance(holder, spender).call(),
+            )
+            .map_err(ChainError::Rpc)
+        };
+
+        let (balance, allowance) = tokio::time::timeout(RPC_TIMEOUT, call)
+            .await
+            .map_err(|_| ChainError::Timeout)??;
+
+        Ok(SettlementCheck {
+            balance_sufficient: balance >= amount,
+            allowance_sufficient: allowance >= amount,
+        })
+    }
+
+    /// Returns `true` when `address` has deployed code on `chain_id`, i.e.
+    /// it is a smart-contract wallet rather than an EOA.
+    pub async fn is_contract(&self, chain_id: u64, address: &str) -> Result<bool, ChainError> {
+        let provider = self.provider(chain_id)?;
+        let address = parse_address(address)?;
+
+        let code: Bytes = tokio::time::timeout(RPC_TIMEOUT, provider.get_code(address, None))
+            .await
+            .map_err(|_| ChainError::Timeout)?
+            .map_err(ChainError::Rpc)?;
+
+        Ok(!code.0.is_empty())
+    }
+
+    /// Calls `isValidSignature(digest, signature)` on the ERC-1271 contract
+    /// at `contract`, returning `true` iff it returns the magic value.
+    pub async fn check_erc1271(
+        &self,
+        chain_id: u64,
+        contract: &str,
+        digest: H256,
+        signature: Bytes,
+    ) -> Result<bool, ChainError> {
+        let provider = self.provider(chain_id)?;
+        let contract = parse_address(contract)?;
+
+        let wallet = IERC1271::new(contract, Arc::clone(provider));
+        let magic_value = tokio::time::timeout(
+            RPC_TIMEOUT,
+            wallet.is_valid_signature(digest.0, signature).call(),
+        )
+        .await
+        .map_err(|_| ChainError::Timeout)?
+        .map_err(ChainError::Rpc)?;
+
+        Ok(magic_value == ERC1271_MAGIC_VALUE)
+    }
+
+    /// Checks `holder`'s native-token balance against `amount`.
+    pub async fn check_native(
+        &self,
+        chain_id: u64,
+        holder: &str,
+        amount: U256,
+    ) -> Result<SettlementCheck, ChainError> {
+        let provider = self.provider(chain_id)?;
+        let holder = parse_address(holder)?;
+
+        let balance = tokio::time::timeout(RPC_TIMEOUT, provider.get_balance(holder, None))
+            .await
+            .map_err(|_| ChainError::Timeout)?
+            .map_err(ChainError::Rpc)?;
+
+        Ok(SettlementCheck {
+            balance_sufficient: balance >= amount,
+            // Native transfers have no allowance step.
+            allowance_sufficient: true,
+        })
+    }
+}
+
+fn parse_address(raw: &str) -> Result<Address, ChainError> {
+    Address::from_str(raw).map_err(|_| ChainError::InvalidAddress(raw.to_string()))
+}
+
+/// `token == "native"` (case-insensitive) designates the chain's native
+/// currency rather than an ERC-20 contract address.
+pub fn is_native_token(token: &str) -> bool {
+    token.eq_ignore_ascii_case("native")
+}
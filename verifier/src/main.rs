@@ -1,25 +1,72 @@
+mod chain;
+mod nonce;
+mod receipt;
+mod telemetry;
+mod token;
+
 use axum::{
-    extract::Json,
+    extract::{Json, Path, Query, State},
     http::{HeaderMap, StatusCode}, // VIBE FIX: Added HeaderMap to read headers
     routing::{get, post},
     Router,
 };
+use chain::ChainGateway;
 use ethers::types::transaction::eip712::TypedData;
-use ethers::types::Signature;
+use ethers::types::{Signature, U256};
+use nonce::{NonceCheck, NonceMode, NonceStore};
+use receipt::ReceiptIssuer;
 use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
 use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use token::TokenIssuer;
+use tracing::Instrument;
+use uuid::Uuid;
+
+/// Shared state injected into every handler.
+#[derive(Clone)]
+struct AppState {
+    nonce_store: Arc<NonceStore>,
+    chain_gateway: Arc<ChainGateway>,
+    token_issuer: Arc<TokenIssuer>,
+    receipt_issuer: Arc<ReceiptIssuer>,
+}
+
+/// TTL after which an unused random-mode nonce record is reaped.
+const NONCE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
 
 #[tokio::main]
 async fn main() {
+    telemetry::init();
+
+    let nonce_store = Arc::new(NonceStore::new(NonceMode::from_env(), NONCE_TTL));
+    nonce_store.spawn_reaper();
+    let chain_gateway = Arc::new(ChainGateway::from_env());
+    let token_issuer = Arc::new(TokenIssuer::from_env());
+    let receipt_issuer = Arc::new(ReceiptIssuer::from_env());
+
+    let state = AppState {
+        nonce_store,
+        chain_gateway,
+        token_issuer,
+        receipt_issuer,
+    };
+
     // build our application with a route
     let app = Router::new()
         .route("/health", get(health))
-        .route("/verify", post(verify_signature));
+        .route("/verify", post(verify_signature))
+        .route("/nonce/:address", get(get_highest_nonce))
+        .route("/token/verify", post(verify_token))
+        .route("/revoke", post(revoke_receipt))
+        .route("/status/:index", get(receipt_status))
+        .route("/receipt/verify", post(verify_receipt))
+        .with_state(state);
 
     // run it
     let addr = SocketAddr::from(([0, 0, 0, 0], 3002));
-    println!("Rust Verifier listening on {}", addr);
+    tracing::info!(%addr, "Rust Verifier listening");
     let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
     axum::serve(listener, app).await.unwrap();
 }
@@ -32,6 +79,15 @@ async fn health() -> &'static str {
 struct VerifyRequest {
     context: PaymentContext,
     signature: String,
+    /// When `true`, the payer's on-chain balance (and, for ERC-20 tokens,
+    /// allowance) must cover `amount` for `is_valid` to be `true`.
+    #[serde(default)]
+    require_settlement: bool,
+    /// When `true` and verification succeeds, mint a bearer JWT in the
+    /// response so downstream services don't have to re-verify the
+    /// signature themselves.
+    #[serde(default)]
+    issue_token: bool,
 }
 
 #[derive(Deserialize, Debug)]
@@ -42,6 +98,19 @@ struct PaymentContext {
     nonce: String,
     #[serde(rename = "chainId")]
     chain_id: u64,
+    /// Address allowed to pull `amount` of an ERC-20 `token` from the payer.
+    /// Defaults to `recipient` when omitted.
+    spender: Option<String>,
+    /// The account that signed the payload, when it differs from
+    /// `recipient`. Checked for ERC-1271 smart-contract-wallet signatures
+    /// when plain ECDSA recovery doesn't apply.
+    signer: Option<String>,
+    /// Unix timestamp after which the signature is no longer valid. Hashed
+    /// into the typed data as `uint256` when present, so it is cryptographically
+    /// bound rather than a bare request parameter. Omitted entirely (including
+    /// from the `Payment` type) when absent, so existing clients that don't
+    /// set a deadline keep verifying exactly as before.
+    deadline: Option<u64>,
 }
 
 #[derive(Serialize)]
@@ -49,28 +118,348 @@ struct VerifyResponse {
     is_valid: bool,
     recovered_address: Option<String>,
     error: Option<String>,
+    balance_sufficient: Option<bool>,
+    allowance_sufficient: Option<bool>,
+    /// Which signature scheme validated the request: `"eoa"` or `"erc1271"`.
+    verification_path: Option<String>,
+    /// Bearer JWT proving the payment was authorized, present when
+    /// `issue_token` was set and verification succeeded.
+    token: Option<String>,
+    /// Verifiable Credential receipt, present when `?receipt=true` was set
+    /// and verification succeeded.
+    receipt: Option<receipt::VerifiableCredential>,
+}
+
+/// Query parameters accepted by `POST /verify`.
+#[derive(Deserialize, Debug, Default)]
+struct VerifyQuery {
+    #[serde(default)]
+    receipt: bool,
+}
+
+#[derive(Deserialize, Debug)]
+struct TokenVerifyRequest {
+    token: String,
+}
+
+#[derive(Serialize)]
+struct TokenVerifyResponse {
+    valid: bool,
+    claims: Option<token::PaymentClaims>,
+    error: Option<String>,
+}
+
+async fn verify_token(
+    State(state): State<AppState>,
+    Json(payload): Json<TokenVerifyRequest>,
+) -> (StatusCode, Json<TokenVerifyResponse>) {
+    match state.token_issuer.decode_and_validate(&payload.token) {
+        Ok(claims) => (
+            StatusCode::OK,
+            Json(TokenVerifyResponse {
+                valid: true,
+                claims: Some(claims),
+                error: None,
+            }),
+        ),
+        Err(e) => (
+            StatusCode::UNAUTHORIZED,
+            Json(TokenVerifyResponse {
+                valid: false,
+                claims: None,
+                error: Some(e.to_string()),
+            }),
+        ),
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct NonceQuery {
+    #[serde(rename = "chainId")]
+    chain_id: u64,
+}
+
+#[derive(Serialize)]
+struct NonceResponse {
+    address: String,
+    #[serde(rename = "chainId")]
+    chain_id: u64,
+    highest_nonce: Option<u64>,
+}
+
+/// Returns the highest nonce recorded for `address`. `highest_nonce` is
+/// only ever populated when the server is running with
+/// `NONCE_MODE=monotonic`; under the default `Random` mode it is always
+/// `null`, since opaque nonces have no ordering to report.
+async fn get_highest_nonce(
+    State(state): State<AppState>,
+    Path(address): Path<String>,
+    Query(query): Query<NonceQuery>,
+) -> Json<NonceResponse> {
+    let highest_nonce = state
+        .nonce_store
+        .highest_nonce(&address, query.chain_id)
+        .await;
+
+    Json(NonceResponse {
+        address,
+        chain_id: query.chain_id,
+        highest_nonce,
+    })
+}
+
+#[derive(Deserialize, Debug)]
+struct RevokeRequest {
+    index: u64,
+}
+
+#[derive(Serialize)]
+struct RevokeResponse {
+    index: u64,
+    revoked: bool,
+}
+
+async fn revoke_receipt(
+    State(state): State<AppState>,
+    Json(payload): Json<RevokeRequest>,
+) -> Json<RevokeResponse> {
+    state.receipt_issuer.revoke(payload.index).await;
+    Json(RevokeResponse {
+        index: payload.index,
+        revoked: true,
+    })
+}
+
+#[derive(Serialize)]
+struct StatusResponse {
+    index: u64,
+    revoked: bool,
+}
+
+async fn receipt_status(
+    State(state): State<AppState>,
+    Path(index): Path<u64>,
+) -> Json<StatusResponse> {
+    let revoked = state.receipt_issuer.is_revoked(index).await;
+    Json(StatusResponse { index, revoked })
+}
+
+#[derive(Deserialize, Debug)]
+struct ReceiptVerifyRequest {
+    credential: receipt::VerifiableCredential,
+}
+
+#[derive(Serialize)]
+struct ReceiptVerifyResponse {
+    valid: bool,
+    error: Option<String>,
+}
+
+async fn verify_receipt(
+    State(state): State<AppState>,
+    Json(payload): Json<ReceiptVerifyRequest>,
+) -> (StatusCode, Json<ReceiptVerifyResponse>) {
+    match state.receipt_issuer.verify(&payload.credential).await {
+        Ok(()) => (
+            StatusCode::OK,
+            Json(ReceiptVerifyResponse {
+                valid: true,
+                error: None,
+            }),
+        ),
+        Err(e) => (
+            StatusCode::OK,
+            Json(ReceiptVerifyResponse {
+                valid: false,
+                error: Some(e),
+            }),
+        ),
+    }
+}
+
+/// Mints a bearer token for a verified payment when the caller opted in via
+/// `issue_token`. Logs and drops the token on mint failure rather than
+/// failing an otherwise-successful verification.
+fn issue_token_if_requested(
+    state: &AppState,
+    payload: &VerifyRequest,
+    is_valid: bool,
+    recovered: &str,
+) -> Option<String> {
+    if !(payload.issue_token && is_valid) {
+        return None;
+    }
+
+    match state.token_issuer.issue(
+        recovered,
+        &payload.context.recipient,
+        &payload.context.token,
+        &payload.context.amount,
+        &payload.context.nonce,
+        payload.context.chain_id,
+    ) {
+        Ok(token) => Some(token),
+        Err(e) => {
+            tracing::warn!(error = %e, "failed to issue payment token");
+            None
+        }
+    }
+}
+
+/// Mints a Verifiable Credential receipt for a verified payment when the
+/// caller opted in via `?receipt=true`.
+async fn issue_receipt_if_requested(
+    state: &AppState,
+    payload: &VerifyRequest,
+    query: &VerifyQuery,
+    is_valid: bool,
+    recovered: &str,
+) -> Option<receipt::VerifiableCredential> {
+    if !(query.receipt && is_valid) {
+        return None;
+    }
+
+    let subject = receipt::CredentialSubject {
+        id: recovered.to_string(),
+        recipient: payload.context.recipient.clone(),
+        token: payload.context.token.clone(),
+        amount: payload.context.amount.clone(),
+        nonce: payload.context.nonce.clone(),
+        chain_id: payload.context.chain_id,
+    };
+
+    Some(state.receipt_issuer.issue(subject).await)
+}
+
+/// Outcome of `check_settlement_if_required` for a given signer address.
+enum SettlementOutcome {
+    /// `require_settlement` was not set; no on-chain check was made.
+    Skipped,
+    Ok {
+        balance_sufficient: bool,
+        allowance_sufficient: bool,
+    },
+    /// `amount` was not a valid base-10 `U256` — a client error, not a
+    /// settlement failure.
+    InvalidAmount(String),
+    /// The on-chain balance/allowance check itself failed (RPC error, etc).
+    Error(String),
+}
+
+/// Confirms `address` can actually cover `payload.context.amount` when
+/// `payload.require_settlement` is set. Shared by the EOA and ERC-1271
+/// verification paths so contract-wallet payers are held to the same
+/// settlement gate as EOA payers.
+async fn check_settlement_if_required(
+    state: &AppState,
+    payload: &VerifyRequest,
+    address: &str,
+) -> SettlementOutcome {
+    if !payload.require_settlement {
+        return SettlementOutcome::Skipped;
+    }
+
+    let spender = payload
+        .context
+        .spender
+        .as_deref()
+        .unwrap_or(&payload.context.recipient);
+    let amount = match U256::from_dec_str(&payload.context.amount) {
+        Ok(amount) => amount,
+        Err(e) => return SettlementOutcome::InvalidAmount(format!("Invalid amount: {}", e)),
+    };
+
+    let check = if chain::is_native_token(&payload.context.token) {
+        state
+            .chain_gateway
+            .check_native(payload.context.chain_id, address, amount)
+            .await
+    } else {
+        state
+            .chain_gateway
+            .check_erc20(
+                payload.context.chain_id,
+                &payload.context.token,
+                address,
+                spender,
+                amount,
+            )
+            .await
+    };
+
+    match check {
+        Ok(check) => SettlementOutcome::Ok {
+            balance_sufficient: check.balance_sufficient,
+            allowance_sufficient: check.allowance_sufficient,
+        },
+        Err(e) => SettlementOutcome::Error(e.to_string()),
+    }
+}
+
+/// Clock-skew allowance (seconds) applied on top of `deadline` before a
+/// signature is treated as expired, configurable via `DEADLINE_LEEWAY_SECONDS`
+/// (default 30).
+fn deadline_leeway_seconds() -> u64 {
+    std::env::var("DEADLINE_LEEWAY_SECONDS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(30)
+}
+
+/// Rejects a `deadline` that has already passed, allowing for clock skew.
+/// Payments with no `deadline` never expire.
+fn check_deadline(deadline: Option<u64>) -> Result<(), String> {
+    let Some(deadline) = deadline else {
+        return Ok(());
+    };
+    if now_unix() > deadline.saturating_add(deadline_leeway_seconds()) {
+        Err("signature deadline has passed".to_string())
+    } else {
+        Ok(())
+    }
+}
+
+fn now_unix() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system clock is before the unix epoch")
+        .as_secs()
 }
 
 async fn verify_signature(
+    State(state): State<AppState>,
     headers: HeaderMap,
+    Query(query): Query<VerifyQuery>,
     Json(payload): Json<VerifyRequest>,
 ) -> (StatusCode, HeaderMap, Json<VerifyResponse>) {
-    // Extract ID
+    // Carry the caller's correlation id through the request, generating one
+    // when absent so every verification is traceable even for clients that
+    // don't set the header.
     let correlation_id = headers
         .get("X-Correlation-ID")
         .and_then(|v| v.to_str().ok())
-        .unwrap_or("unknown");
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
 
+    let span = tracing::info_span!("verify_signature", correlation_id = %correlation_id);
+    verify_signature_inner(state, query, payload, correlation_id)
+        .instrument(span)
+        .await
+}
+
+async fn verify_signature_inner(
+    state: AppState,
+    query: VerifyQuery,
+    payload: VerifyRequest,
+    correlation_id: String,
+) -> (StatusCode, HeaderMap, Json<VerifyResponse>) {
     // Prepare response header
     let mut res_headers = HeaderMap::new();
     if let Ok(header_value) = correlation_id.parse() {
         res_headers.insert("X-Correlation-ID", header_value);
     }
 
-    println!(
-        "[CorrelationID: {}] Received verification request for nonce: {}",
-        correlation_id, payload.context.nonce
-    );
+    tracing::info!(nonce = %payload.context.nonce, "received verification request");
 
     // Reconstruct Typed Data (Domain, Types, Value)
     let domain = serde_json::json!({
@@ -80,21 +469,27 @@ async fn verify_signature(
         "verifyingContract": "0x0000000000000000000000000000000000000000"
     });
 
-    let types = serde_json::json!({
-        "Payment": [
-            { "name": "recipient", "type": "address" },
-            { "name": "token", "type": "string" },
-            { "name": "amount", "type": "string" },
-            { "name": "nonce", "type": "string" }
-        ]
-    });
-
-    let value = serde_json::json!({
+    let mut payment_fields = serde_json::json!([
+        { "name": "recipient", "type": "address" },
+        { "name": "token", "type": "string" },
+        { "name": "amount", "type": "string" },
+        { "name": "nonce", "type": "string" }
+    ]);
+    let mut value = serde_json::json!({
         "recipient": payload.context.recipient,
         "token": payload.context.token,
         "amount": payload.context.amount,
         "nonce": payload.context.nonce
     });
+    if let Some(deadline) = payload.context.deadline {
+        payment_fields
+            .as_array_mut()
+            .unwrap()
+            .push(serde_json::json!({ "name": "deadline", "type": "uint256" }));
+        value["deadline"] = serde_json::json!(deadline);
+    }
+
+    let types = serde_json::json!({ "Payment": payment_fields });
 
     let typed_data_json = serde_json::json!({
         "domain": domain,
@@ -114,6 +509,11 @@ async fn verify_signature(
                     is_valid: false,
                     recovered_address: None,
                     error: Some(format!("Failed to build typed data: {}", e)),
+                    balance_sufficient: None,
+                    allowance_sufficient: None,
+                    verification_path: None,
+                    token: None,
+                    receipt: None,
                 }),
             );
         }
@@ -130,6 +530,11 @@ async fn verify_signature(
                     is_valid: false,
                     recovered_address: None,
                     error: Some(format!("Invalid signature format: {}", e)),
+                    balance_sufficient: None,
+                    allowance_sufficient: None,
+                    verification_path: None,
+                    token: None,
+                    receipt: None,
                 }),
             );
         }
@@ -138,25 +543,360 @@ async fn verify_signature(
     // Final Verification
     match signature.recover_typed_data(&typed_data) {
         Ok(address) => {
-            println!(
-                "[CorrelationID: {}] Signature valid! Recovered: {:?}",
-                correlation_id, address
-            );
+            let recovered = format!("{:?}", address);
+
+            if let Err(e) = check_deadline(payload.context.deadline) {
+                tracing::warn!(error = %e, address = %recovered, "rejecting expired signature");
+                return (
+                    StatusCode::FORBIDDEN,
+                    res_headers,
+                    Json(VerifyResponse {
+                        is_valid: false,
+                        recovered_address: Some(recovered),
+                        error: Some(e),
+                        balance_sufficient: None,
+                        allowance_sufficient: None,
+                        verification_path: None,
+                        token: None,
+                        receipt: None,
+                    }),
+                );
+            }
+
+            // Settlement gating: confirm the signer can actually pay before
+            // trusting the signature alone.
+            let (balance_sufficient, allowance_sufficient) =
+                match check_settlement_if_required(&state, &payload, &recovered).await {
+                    SettlementOutcome::Skipped => (None, None),
+                    SettlementOutcome::Ok {
+                        balance_sufficient,
+                        allowance_sufficient,
+                    } => (Some(balance_sufficient), Some(allowance_sufficient)),
+                    SettlementOutcome::InvalidAmount(e) => {
+                        return (
+                            StatusCode::BAD_REQUEST,
+                            res_headers,
+                            Json(VerifyResponse {
+                                is_valid: false,
+                                recovered_address: Some(recovered),
+                                error: Some(e),
+                                balance_sufficient: None,
+                                allowance_sufficient: None,
+                                verification_path: None,
+                                token: None,
+                                receipt: None,
+                            }),
+                        );
+                    }
+                    SettlementOutcome::Error(e) => {
+                        tracing::warn!(error = %e, "settlement check failed");
+                        return (
+                            StatusCode::OK,
+                            res_headers,
+                            Json(VerifyResponse {
+                                is_valid: false,
+                                recovered_address: Some(recovered),
+                                error: Some(e),
+                                balance_sufficient: None,
+                                allowance_sufficient: None,
+                                verification_path: None,
+                                token: None,
+                                receipt: None,
+                            }),
+                        );
+                    }
+                };
+
+            let is_valid =
+                balance_sufficient.unwrap_or(true) && allowance_sufficient.unwrap_or(true);
+
+            // Replay protection: only burn the nonce once the payment is
+            // actually authorized, so a request rejected for insufficient
+            // balance/allowance can be legitimately retried after funding
+            // without being mistaken for a replay.
+            if is_valid {
+                match state
+                    .nonce_store
+                    .check_and_insert(&recovered, payload.context.chain_id, &payload.context.nonce)
+                    .await
+                {
+                    NonceCheck::AlreadyUsed => {
+                        tracing::warn!(
+                            nonce = %payload.context.nonce,
+                            address = %recovered,
+                            "rejected replayed nonce"
+                        );
+                        return (
+                            StatusCode::CONFLICT,
+                            res_headers,
+                            Json(VerifyResponse {
+                                is_valid: false,
+                                recovered_address: Some(recovered),
+                                error: Some("nonce already used".to_string()),
+                                balance_sufficient,
+                                allowance_sufficient,
+                                verification_path: None,
+                                token: None,
+                                receipt: None,
+                            }),
+                        );
+                    }
+                    NonceCheck::Malformed => {
+                        tracing::warn!(
+                            nonce = %payload.context.nonce,
+                            address = %recovered,
+                            "rejected malformed nonce"
+                        );
+                        return (
+                            StatusCode::BAD_REQUEST,
+                            res_headers,
+                            Json(VerifyResponse {
+                                is_valid: false,
+                                recovered_address: Some(recovered),
+                                error: Some("nonce is not a valid counter value".to_string()),
+                                balance_sufficient,
+                                allowance_sufficient,
+                                verification_path: None,
+                                token: None,
+                                receipt: None,
+                            }),
+                        );
+                    }
+                    NonceCheck::Accepted => {}
+                }
+            }
+
+            let token = issue_token_if_requested(&state, &payload, is_valid, &recovered);
+            let receipt =
+                issue_receipt_if_requested(&state, &payload, &query, is_valid, &recovered).await;
+
+            tracing::info!(address = %recovered, "signature valid");
             (
                 StatusCode::OK,
                 res_headers, // Header added
                 Json(VerifyResponse {
-                    is_valid: true,
-                    recovered_address: Some(format!("{:?}", address)),
+                    is_valid,
+                    recovered_address: Some(recovered),
                     error: None,
+                    balance_sufficient,
+                    allowance_sufficient,
+                    verification_path: Some("eoa".to_string()),
+                    token,
+                    receipt,
                 }),
             )
         }
         Err(e) => {
-            println!(
-                "[CorrelationID: {}] Verification failed: {}",
-                correlation_id, e
-            );
+            // ECDSA recovery failed; this may be a smart-contract wallet
+            // signature instead (e.g. Gnosis Safe, Argent), which doesn't
+            // recover to an address at all. Fall back to ERC-1271.
+            let candidate = payload
+                .context
+                .signer
+                .as_deref()
+                .unwrap_or(&payload.context.recipient);
+
+            if let Err(deadline_err) = check_deadline(payload.context.deadline) {
+                tracing::warn!(error = %deadline_err, address = %candidate, "rejecting expired signature");
+                return (
+                    StatusCode::FORBIDDEN,
+                    res_headers,
+                    Json(VerifyResponse {
+                        is_valid: false,
+                        recovered_address: None,
+                        error: Some(deadline_err),
+                        balance_sufficient: None,
+                        allowance_sufficient: None,
+                        verification_path: None,
+                        token: None,
+                        receipt: None,
+                    }),
+                );
+            }
+
+            if let Ok(digest) = typed_data.encode_eip712() {
+                match state
+                    .chain_gateway
+                    .is_contract(payload.context.chain_id, candidate)
+                    .await
+                {
+                    Ok(true) => {
+                        match state
+                            .chain_gateway
+                            .check_erc1271(
+                                payload.context.chain_id,
+                                candidate,
+                                ethers::types::H256::from(digest),
+                                payload.signature.parse().unwrap_or_default(),
+                            )
+                            .await
+                        {
+                            Ok(true) => {
+                                // Settlement gating: same guarantee as the
+                                // EOA path — a contract wallet's signature
+                                // alone isn't enough when the caller asked
+                                // us to confirm it can actually pay.
+                                let (balance_sufficient, allowance_sufficient) =
+                                    match check_settlement_if_required(&state, &payload, candidate)
+                                        .await
+                                    {
+                                        SettlementOutcome::Skipped => (None, None),
+                                        SettlementOutcome::Ok {
+                                            balance_sufficient,
+                                            allowance_sufficient,
+                                        } => (Some(balance_sufficient), Some(allowance_sufficient)),
+                                        SettlementOutcome::InvalidAmount(e) => {
+                                            return (
+                                                StatusCode::BAD_REQUEST,
+                                                res_headers,
+                                                Json(VerifyResponse {
+                                                    is_valid: false,
+                                                    recovered_address: Some(candidate.to_string()),
+                                                    error: Some(e),
+                                                    balance_sufficient: None,
+                                                    allowance_sufficient: None,
+                                                    verification_path: None,
+                                                    token: None,
+                                                    receipt: None,
+                                                }),
+                                            );
+                                        }
+                                        SettlementOutcome::Error(e) => {
+                                            tracing::warn!(
+                                                error = %e,
+                                                "settlement check failed"
+                                            );
+                                            return (
+                                                StatusCode::OK,
+                                                res_headers,
+                                                Json(VerifyResponse {
+                                                    is_valid: false,
+                                                    recovered_address: Some(candidate.to_string()),
+                                                    error: Some(e),
+                                                    balance_sufficient: None,
+                                                    allowance_sufficient: None,
+                                                    verification_path: None,
+                                                    token: None,
+                                                    receipt: None,
+                                                }),
+                                            );
+                                        }
+                                    };
+
+                                let is_valid = balance_sufficient.unwrap_or(true)
+                                    && allowance_sufficient.unwrap_or(true);
+
+                                // Replay protection: same guarantee as the EOA
+                                // path, keyed on the contract wallet address,
+                                // and only burned once the payment is
+                                // actually authorized.
+                                if is_valid {
+                                    match state
+                                        .nonce_store
+                                        .check_and_insert(
+                                            candidate,
+                                            payload.context.chain_id,
+                                            &payload.context.nonce,
+                                        )
+                                        .await
+                                    {
+                                        NonceCheck::AlreadyUsed => {
+                                            tracing::warn!(
+                                                nonce = %payload.context.nonce,
+                                                address = %candidate,
+                                                "rejected replayed nonce"
+                                            );
+                                            return (
+                                                StatusCode::CONFLICT,
+                                                res_headers,
+                                                Json(VerifyResponse {
+                                                    is_valid: false,
+                                                    recovered_address: Some(candidate.to_string()),
+                                                    error: Some("nonce already used".to_string()),
+                                                    balance_sufficient,
+                                                    allowance_sufficient,
+                                                    verification_path: None,
+                                                    token: None,
+                                                    receipt: None,
+                                                }),
+                                            );
+                                        }
+                                        NonceCheck::Malformed => {
+                                            tracing::warn!(
+                                                nonce = %payload.context.nonce,
+                                                address = %candidate,
+                                                "rejected malformed nonce"
+                                            );
+                                            return (
+                                                StatusCode::BAD_REQUEST,
+                                                res_headers,
+                                                Json(VerifyResponse {
+                                                    is_valid: false,
+                                                    recovered_address: Some(candidate.to_string()),
+                                                    error: Some(
+                                                        "nonce is not a valid counter value"
+                                                            .to_string(),
+                                                    ),
+                                                    balance_sufficient,
+                                                    allowance_sufficient,
+                                                    verification_path: None,
+                                                    token: None,
+                                                    receipt: None,
+                                                }),
+                                            );
+                                        }
+                                        NonceCheck::Accepted => {}
+                                    }
+                                }
+
+                                tracing::info!(address = %candidate, "erc-1271 signature valid");
+                                let token = issue_token_if_requested(
+                                    &state,
+                                    &payload,
+                                    is_valid,
+                                    candidate,
+                                );
+                                let receipt = issue_receipt_if_requested(
+                                    &state, &payload, &query, is_valid, candidate,
+                                )
+                                .await;
+                                return (
+                                    StatusCode::OK,
+                                    res_headers,
+                                    Json(VerifyResponse {
+                                        is_valid,
+                                        recovered_address: Some(candidate.to_string()),
+                                        error: None,
+                                        balance_sufficient,
+                                        allowance_sufficient,
+                                        verification_path: Some("erc1271".to_string()),
+                                        token,
+                                        receipt,
+                                    }),
+                                );
+                            }
+                            Ok(false) => {}
+                            Err(chain_err) => {
+                                tracing::warn!(
+                                    error = %chain_err,
+                                    address = %candidate,
+                                    "erc-1271 check failed"
+                                );
+                            }
+                        }
+                    }
+                    Ok(false) => {}
+                    Err(chain_err) => {
+                        tracing::warn!(
+                            error = %chain_err,
+                            address = %candidate,
+                            "could not determine if address is a contract"
+                        );
+                    }
+                }
+            }
+
+            tracing::warn!(error = %e, "verification failed");
             (
                 StatusCode::OK,
                 res_headers, // Header added
@@ -164,6 +904,11 @@ async fn verify_signature(
                     is_valid: false,
                     recovered_address: None,
                     error: Some(format!("Verification failed: {}", e)),
+                    balance_sufficient: None,
+                    allowance_sufficient: None,
+                    verification_path: None,
+                    token: None,
+                    receipt: None,
                 }),
             )
         }
@@ -176,6 +921,17 @@ mod tests {
     use ethers::signers::{LocalWallet, Signer};
     use ethers::types::transaction::eip712::TypedData;
 
+    /// Tests use opaque string nonces, so pin the store to `Random` mode
+    /// rather than reading `NONCE_MODE` from the environment.
+    fn test_state() -> State<AppState> {
+        State(AppState {
+            nonce_store: Arc::new(NonceStore::new(NonceMode::Random, NONCE_TTL)),
+            chain_gateway: Arc::new(ChainGateway::from_env()),
+            token_issuer: Arc::new(TokenIssuer::from_env()),
+            receipt_issuer: Arc::new(ReceiptIssuer::from_env()),
+        })
+    }
+
     #[tokio::test]
     async fn test_verify_signature_valid() {
         let wallet: LocalWallet =
@@ -227,19 +983,100 @@ mod tests {
                 amount: "100".to_string(),
                 nonce: "unique-nonce-123".to_string(),
                 chain_id: 1,
+                spender: None,
+                signer: None,
+                deadline: None,
             },
             signature: signature_str,
+            require_settlement: false,
+            issue_token: false,
         };
 
         // For tests, we pass empty headers
         let (status, _headers, Json(response)) =
-            verify_signature(HeaderMap::new(), Json(req)).await;
+            verify_signature(test_state(), HeaderMap::new(), Query(VerifyQuery::default()), Json(req)).await;
 
         assert_eq!(status, StatusCode::OK);
         assert!(response.is_valid);
         assert_eq!(response.error, None);
     }
 
+    #[tokio::test]
+    async fn test_verify_signature_expired_deadline_rejected() {
+        let wallet: LocalWallet =
+            "380eb0f3d505f087e438eca80bc4df9a7faa24f868e69fc0440261a0fc0567dc"
+                .parse()
+                .unwrap();
+        let wallet = wallet.with_chain_id(1u64);
+
+        let json_typed_data = serde_json::json!({
+            "domain": {
+                "name": "MicroAI Paygate",
+                "version": "1",
+                "chainId": 1,
+                "verifyingContract": "0x0000000000000000000000000000000000000000"
+            },
+            "types": {
+                "EIP712Domain": [
+                    { "name": "name", "type": "string" },
+                    { "name": "version", "type": "string" },
+                    { "name": "chainId", "type": "uint256" },
+                    { "name": "verifyingContract", "type": "address" }
+                ],
+                "Payment": [
+                    { "name": "recipient", "type": "address" },
+                    { "name": "token", "type": "string" },
+                    { "name": "amount", "type": "string" },
+                    { "name": "nonce", "type": "string" },
+                    { "name": "deadline", "type": "uint256" }
+                ]
+            },
+            "primaryType": "Payment",
+            "message": {
+                "recipient": "0x1234567890123456789012345678901234567890",
+                "token": "USDC",
+                "amount": "100",
+                "nonce": "expired-deadline-nonce",
+                "deadline": 1
+            }
+        });
+
+        let typed_data: TypedData = serde_json::from_value(json_typed_data).unwrap();
+
+        let signature = wallet.sign_typed_data(&typed_data).await.unwrap();
+        let signature_str = format!("0x{}", hex::encode(signature.to_vec()));
+
+        let req = VerifyRequest {
+            context: PaymentContext {
+                recipient: "0x1234567890123456789012345678901234567890".to_string(),
+                token: "USDC".to_string(),
+                amount: "100".to_string(),
+                nonce: "expired-deadline-nonce".to_string(),
+                chain_id: 1,
+                spender: None,
+                signer: None,
+                deadline: Some(1),
+            },
+            signature: signature_str,
+            require_settlement: false,
+            issue_token: false,
+        };
+
+        let (status, _headers, Json(response)) =
+            verify_signature(test_state(), HeaderMap::new(), Query(VerifyQuery::default()), Json(req)).await;
+
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert!(!response.is_valid);
+        assert_eq!(response.error, Some("signature deadline has passed".to_string()));
+    }
+
+    #[test]
+    fn test_check_deadline_far_future_does_not_overflow() {
+        // A "never expires" sentinel deadline must not wrap around when
+        // added to the leeway.
+        assert!(check_deadline(Some(u64::MAX)).is_ok());
+    }
+
     #[tokio::test]
     async fn test_verify_signature_invalid() {
         let req = VerifyRequest {
@@ -249,12 +1086,17 @@ mod tests {
                 amount: "100".to_string(),
                 nonce: "nonce".to_string(),
                 chain_id: 1,
+                spender: None,
+                signer: None,
+                deadline: None,
             },
             signature: "0x1234567890".to_string(),
+            require_settlement: false,
+            issue_token: false,
         };
 
         let (status, _headers, Json(_response)) =
-            verify_signature(HeaderMap::new(), Json(req)).await;
+            verify_signature(test_state(), HeaderMap::new(), Query(VerifyQuery::default()), Json(req)).await;
         assert_eq!(status, StatusCode::BAD_REQUEST);
     }
 
@@ -279,11 +1121,16 @@ mod tests {
                 amount: "100".to_string(),
                 nonce: "nonce".to_string(),
                 chain_id: 1,
+                spender: None,
+                signer: None,
+                deadline: None,
             },
             signature: "0x1234567890".to_string(),
+            require_settlement: false,
+            issue_token: false,
         };
 
-        let (_status, response_headers, _json) = verify_signature(headers, Json(req)).await;
+        let (_status, response_headers, _json) = verify_signature(test_state(), headers, Query(VerifyQuery::default()), Json(req)).await;
 
         // Verify correlation ID is in response headers
         let response_id = response_headers.get("X-Correlation-ID");
@@ -299,9 +1146,9 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_correlation_id_unknown_when_missing() {
-        // Test that when no correlation ID is provided, "unknown" is used
-        // but no header is returned (since "unknown" won't parse to a valid header)
+    async fn test_correlation_id_generated_when_missing() {
+        // When no correlation ID is provided, a UUID is generated and echoed
+        // back in the response header instead of falling back to "unknown".
         let headers = HeaderMap::new(); // Empty headers
 
         let req = VerifyRequest {
@@ -311,22 +1158,26 @@ mod tests {
                 amount: "100".to_string(),
                 nonce: "nonce".to_string(),
                 chain_id: 1,
+                spender: None,
+                signer: None,
+                deadline: None,
             },
             signature: "0x1234567890".to_string(),
+            require_settlement: false,
+            issue_token: false,
         };
 
-        let (_status, response_headers, _json) = verify_signature(headers, Json(req)).await;
+        let (_status, response_headers, _json) = verify_signature(test_state(), headers, Query(VerifyQuery::default()), Json(req)).await;
 
-        // When "unknown" is used as fallback, it should still be set in response
+        // A generated correlation ID should still be echoed back as a header.
         let response_id = response_headers.get("X-Correlation-ID");
         assert!(
             response_id.is_some(),
-            "Expected X-Correlation-ID header even with unknown value"
+            "Expected X-Correlation-ID header even when none was supplied"
         );
-        assert_eq!(
-            response_id.unwrap().to_str().unwrap(),
-            "unknown",
-            "Should use 'unknown' as fallback correlation ID"
+        assert!(
+            Uuid::parse_str(response_id.unwrap().to_str().unwrap()).is_ok(),
+            "Missing correlation ID should be replaced with a generated UUID"
         );
     }
 
@@ -387,11 +1238,16 @@ mod tests {
                 amount: "100".to_string(),
                 nonce: "correlation-test-nonce".to_string(),
                 chain_id: 1,
+                spender: None,
+                signer: None,
+                deadline: None,
             },
             signature: signature_str,
+            require_settlement: false,
+            issue_token: false,
         };
 
-        let (status, response_headers, Json(response)) = verify_signature(headers, Json(req)).await;
+        let (status, response_headers, Json(response)) = verify_signature(test_state(), headers, Query(VerifyQuery::default()), Json(req)).await;
 
         // Verify successful response
         assert_eq!(status, StatusCode::OK);
@@ -426,11 +1282,16 @@ mod tests {
                 amount: "100".to_string(),
                 nonce: "nonce".to_string(),
                 chain_id: 1,
+                spender: None,
+                signer: None,
+                deadline: None,
             },
             signature: "0x1234567890".to_string(),
+            require_settlement: false,
+            issue_token: false,
         };
 
-        let (_status, response_headers, _json) = verify_signature(headers, Json(req)).await;
+        let (_status, response_headers, _json) = verify_signature(test_state(), headers, Query(VerifyQuery::default()), Json(req)).await;
 
         let response_id = response_headers.get("X-Correlation-ID");
         assert!(response_id.is_some());
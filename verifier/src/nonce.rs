@@ -0,0 +1,219 @@
+//! Replay-protection store for `(address, chain_id, nonce)` tuples.
+//!
+//! Mirrors the nonce-tracking role OpenEthereum's `SigningQueue`/nonce
+//! service plays for pending transactions: before a signed payload is
+//! accepted, its nonce is atomically checked against what has already been
+//! spent for that signer, then recorded so it cannot be replayed.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::sync::Mutex;
+
+/// How nonces are validated for a given `(address, chain_id)` pair.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NonceMode {
+    /// The nonce is a monotonic counter; only values strictly greater than
+    /// the highest one seen so far are accepted.
+    Monotonic,
+    /// The nonce is opaque (e.g. random); only exact repeats are rejected.
+    Random,
+}
+
+impl NonceMode {
+    /// Reads the mode from `NONCE_MODE` (`"monotonic"` or `"random"`),
+    /// defaulting to `Random` when unset or unrecognized, since the rest of
+    /// the service treats nonces as opaque strings rather than counters.
+    pub fn from_env() -> Self {
+        match std::env::var("NONCE_MODE").as_deref() {
+            Ok("monotonic") => NonceMode::Monotonic,
+            _ => NonceMode::Random,
+        }
+    }
+}
+
+/// Outcome of a `check_and_insert` call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NonceCheck {
+    /// The nonce had not been seen before and is now recorded.
+    Accepted,
+    /// The nonce has already been spent (or, in monotonic mode, is not
+    /// greater than the highest nonce already recorded).
+    AlreadyUsed,
+    /// In monotonic mode, the nonce is not a valid `u64` counter value.
+    /// Distinct from `AlreadyUsed`: the nonce was never recorded, so the
+    /// caller should fix its request rather than treat this as a replay.
+    Malformed,
+}
+
+#[derive(Debug, Default)]
+struct NonceRecord {
+    /// Monotonic mode: highest nonce accepted so far.
+    highest: Option<u64>,
+    /// Random mode: nonces seen, each timestamped for TTL eviction.
+    seen: HashMap<String, Instant>,
+}
+
+/// Concurrent, TTL-reaped store of spent nonces, keyed by recovered address
+/// and chain id.
+pub struct NonceStore {
+    mode: NonceMode,
+    ttl: Duration,
+    records: Mutex<HashMap<(String, u64), NonceRecord>>,
+}
+
+impl NonceStore {
+    pub fn new(mode: NonceMode, ttl: Duration) -> Self {
+        Self {
+            mode,
+            ttl,
+            records: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Spawns a background task that periodically evicts nonce records
+    /// older than `ttl`, so the `Random`-mode set does not grow unbounded.
+    ///
+    /// `Monotonic`-mode records are intentionally exempt: a record only
+    /// stores the single highest nonce seen per `(address, chain_id)`, and
+    /// forgetting it would let an old, already-spent nonce be replayed.
+    /// That is one `u64` per distinct signer, which is acceptable to retain
+    /// indefinitely; operators running `NONCE_MODE=monotonic` against an
+    /// unbounded set of signer addresses should budget memory accordingly.
+    pub fn spawn_reaper(self: &Arc<Self>) {
+        let store = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(store.ttl.max(Duration::from_secs(1)));
+            loop {
+                interval.tick().await;
+                store.reap().await;
+            }
+        });
+    }
+
+    /// Evicts expired `Random`-mode entries. `Monotonic`-mode entries
+    /// (`record.highest.is_some()`) are deliberately kept forever — see
+    /// `spawn_reaper`.
+    async fn reap(&self) {
+        let now = Instant::now();
+        let mut records = self.records.lock().await;
+        records.retain(|_, record| {
+            record
+                .seen
+                .retain(|_, inserted| now.duration_since(*inserted) < self.ttl);
+            record.highest.is_some() || !record.seen.is_empty()
+        });
+    }
+
+    /// Atomically checks whether `nonce` has already been spent for
+    /// `(address, chain_id)` and, if not, records it as spent.
+    pub async fn check_and_insert(&self, address: &str, chain_id: u64, nonce: &str) -> NonceCheck {
+        let key = (address.to_lowercase(), chain_id);
+        let mut records = self.records.lock().await;
+        let record = records.entry(key).or_default();
+
+        match self.mode {
+            NonceMode::Monotonic => {
+                let value: u64 = match nonce.parse() {
+                    Ok(v) => v,
+                    Err(_) => return NonceCheck::Malformed,
+                };
+                if record.highest.is_some_and(|highest| value <= highest) {
+                    NonceCheck::AlreadyUsed
+                } else {
+                    record.highest = Some(value);
+                    NonceCheck::Accepted
+                }
+            }
+            NonceMode::Random => {
+                if record.seen.contains_key(nonce) {
+                    NonceCheck::AlreadyUsed
+                } else {
+                    record.seen.insert(nonce.to_string(), Instant::now());
+                    NonceCheck::Accepted
+                }
+            }
+        }
+    }
+
+    /// Returns the highest nonce recorded for `(address, chain_id)`, if any.
+    ///
+    /// Only populated in `Monotonic` mode — the default `Random` mode has
+    /// no notion of a "highest" nonce, so this always returns `None` when
+    /// the store was built with `NonceMode::Random` (the default unless
+    /// `NONCE_MODE=monotonic` is set).
+    pub async fn highest_nonce(&self, address: &str, chain_id: u64) -> Option<u64> {
+        let records = self.records.lock().await;
+        records
+            .get(&(address.to_lowercase(), chain_id))
+            .and_then(|record| record.highest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn monotonic_rejects_non_increasing_nonce() {
+        let store = NonceStore::new(NonceMode::Monotonic, Duration::from_secs(60));
+        assert_eq!(
+            store.check_and_insert("0xabc", 1, "1").await,
+            NonceCheck::Accepted
+        );
+        assert_eq!(
+            store.check_and_insert("0xabc", 1, "1").await,
+            NonceCheck::AlreadyUsed
+        );
+        assert_eq!(
+            store.check_and_insert("0xabc", 1, "0").await,
+            NonceCheck::AlreadyUsed
+        );
+        assert_eq!(
+            store.check_and_insert("0xabc", 1, "2").await,
+            NonceCheck::Accepted
+        );
+        assert_eq!(store.highest_nonce("0xabc", 1).await, Some(2));
+    }
+
+    #[tokio::test]
+    async fn monotonic_reports_malformed_nonce_distinct_from_replay() {
+        let store = NonceStore::new(NonceMode::Monotonic, Duration::from_secs(60));
+        assert_eq!(
+            store.check_and_insert("0xabc", 1, "not-a-number").await,
+            NonceCheck::Malformed
+        );
+        // A malformed nonce must not be recorded as spent.
+        assert_eq!(store.highest_nonce("0xabc", 1).await, None);
+    }
+
+    #[tokio::test]
+    async fn random_rejects_exact_repeats_only() {
+        let store = NonceStore::new(NonceMode::Random, Duration::from_secs(60));
+        assert_eq!(
+            store.check_and_insert("0xabc", 1, "unique-nonce-1").await,
+            NonceCheck::Accepted
+        );
+        assert_eq!(
+            store.check_and_insert("0xabc", 1, "unique-nonce-1").await,
+            NonceCheck::AlreadyUsed
+        );
+        assert_eq!(
+            store.check_and_insert("0xabc", 1, "unique-nonce-2").await,
+            NonceCheck::Accepted
+        );
+    }
+
+    #[tokio::test]
+    async fn reaper_evicts_expired_random_nonces() {
+        let store = NonceStore::new(NonceMode::Random, Duration::from_millis(10));
+        store.check_and_insert("0xabc", 1, "n").await;
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        store.reap().await;
+        assert_eq!(
+            store.check_and_insert("0xabc", 1, "n").await,
+            NonceCheck::Accepted
+        );
+    }
+}
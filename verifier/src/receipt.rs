@@ -0,0 +1,204 @@
+//! Verifiable Credential payment receipts.
+//!
+//! Follows the W3C VC data model: a successful verification can be turned
+//! into a tamper-evident `PaymentAuthorization` credential with a proof and
+//! a `credentialStatus` entry in a server-maintained revocation list, so a
+//! payment authorization can later be revoked.
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tokio::sync::Mutex;
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CredentialSubject {
+    /// Recovered signer address.
+    pub id: String,
+    pub recipient: String,
+    pub token: String,
+    pub amount: String,
+    pub nonce: String,
+    #[serde(rename = "chainId")]
+    pub chain_id: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CredentialStatus {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub status_type: String,
+    pub status_list_index: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Proof {
+    #[serde(rename = "type")]
+    pub proof_type: String,
+    pub created: u64,
+    pub verification_method: String,
+    pub proof_purpose: String,
+    pub proof_value: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifiableCredential {
+    #[serde(rename = "@context")]
+    pub context: Vec<String>,
+    #[serde(rename = "type")]
+    pub credential_type: Vec<String>,
+    pub issuer: String,
+    pub issuance_date: String,
+    pub credential_subject: CredentialSubject,
+    pub credential_status: CredentialStatus,
+    pub proof: Proof,
+}
+
+/// Issues `PaymentAuthorization` credentials and maintains their
+/// revocation status.
+pub struct ReceiptIssuer {
+    secret: Vec<u8>,
+    issuer_did: String,
+    status_list_url: String,
+    revoked: Mutex<HashSet<u64>>,
+    next_index: AtomicU64,
+}
+
+impl ReceiptIssuer {
+    /// Builds an issuer from `RECEIPT_HMAC_SECRET` (proof signing key),
+    /// `RECEIPT_ISSUER` (the `issuer` DID/URL), and `RECEIPT_STATUS_LIST_URL`
+    /// (base URL for `credentialStatus` entries).
+    pub fn from_env() -> Self {
+        let secret = std::env::var("RECEIPT_HMAC_SECRET")
+            .unwrap_or_else(|_| "dev-receipt-secret-change-me".to_string())
+            .into_bytes();
+        let issuer_did = std::env::var("RECEIPT_ISSUER")
+            .unwrap_or_else(|_| "did:web:paygate.example".to_string());
+        let status_list_url = std::env::var("RECEIPT_STATUS_LIST_URL")
+            .unwrap_or_else(|_| "https://paygate.example/status".to_string());
+
+        Self {
+            secret,
+            issuer_did,
+            status_list_url,
+            revoked: Mutex::new(HashSet::new()),
+            next_index: AtomicU64::new(0),
+        }
+    }
+
+    /// Assembles and signs a new credential for `subject`.
+    pub async fn issue(&self, subject: CredentialSubject) -> VerifiableCredential {
+        let index = self.next_index.fetch_add(1, Ordering::SeqCst);
+        let created = now_unix();
+        let issuance_date = to_rfc3339(created);
+        let context = vec!["https://www.w3.org/2018/credentials/v1".to_string()];
+        let credential_type = vec![
+            "VerifiableCredential".to_string(),
+            "PaymentAuthorization".to_string(),
+        ];
+        let credential_status = CredentialStatus {
+            id: format!("{}/{}", self.status_list_url, index),
+            status_type: "RevocationList2020Status".to_string(),
+            status_list_index: index,
+        };
+
+        let proof_value = self.sign(&SignablePayload {
+            context: &context,
+            credential_type: &credential_type,
+            issuer: &self.issuer_did,
+            issuance_date: &issuance_date,
+            credential_subject: &subject,
+            credential_status: &credential_status,
+        });
+
+        VerifiableCredential {
+            context,
+            credential_type,
+            issuer: self.issuer_did.clone(),
+            issuance_date,
+            credential_status,
+            proof: Proof {
+                proof_type: "HMAC-SHA256".to_string(),
+                created,
+                verification_method: self.issuer_did.clone(),
+                proof_purpose: "assertionMethod".to_string(),
+                proof_value,
+            },
+            credential_subject: subject,
+        }
+    }
+
+    /// Re-checks a submitted credential's proof and revocation status.
+    pub async fn verify(&self, credential: &VerifiableCredential) -> Result<(), String> {
+        let expected = self.sign(&SignablePayload {
+            context: &credential.context,
+            credential_type: &credential.credential_type,
+            issuer: &credential.issuer,
+            issuance_date: &credential.issuance_date,
+            credential_subject: &credential.credential_subject,
+            credential_status: &credential.credential_status,
+        });
+        if expected != credential.proof.proof_value {
+            return Err("credential proof does not match".to_string());
+        }
+        if self.is_revoked(credential.credential_status.status_list_index).await {
+            return Err("credential has been revoked".to_string());
+        }
+        Ok(())
+    }
+
+    pub async fn revoke(&self, index: u64) {
+        self.revoked.lock().await.insert(index);
+    }
+
+    pub async fn is_revoked(&self, index: u64) -> bool {
+        self.revoked.lock().await.contains(&index)
+    }
+
+    /// Signs the full credential body (everything but the proof itself), so
+    /// `issuer`, `issuanceDate`, and `credentialStatus.id` are tamper-evident
+    /// alongside the subject, not just the subject in isolation.
+    fn sign(&self, payload: &SignablePayload) -> String {
+        let bytes = serde_json::to_vec(payload).unwrap_or_default();
+        let mut mac =
+            HmacSha256::new_from_slice(&self.secret).expect("HMAC accepts a key of any length");
+        mac.update(&bytes);
+        hex::encode(mac.finalize().into_bytes())
+    }
+}
+
+/// The subset of a [`VerifiableCredential`] that gets bound into its proof —
+/// everything except the proof itself.
+#[derive(Serialize)]
+struct SignablePayload<'a> {
+    #[serde(rename = "@context")]
+    context: &'a [String],
+    #[serde(rename = "type")]
+    credential_type: &'a [String],
+    issuer: &'a str,
+    issuance_date: &'a str,
+    credential_subject: &'a CredentialSubject,
+    credential_status: &'a CredentialStatus,
+}
+
+fn now_unix() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system clock is before the unix epoch")
+        .as_secs()
+}
+
+/// Formats a unix timestamp as an RFC3339 `xsd:dateTime`, as required by the
+/// W3C VC data model's `issuanceDate`.
+fn to_rfc3339(unix_secs: u64) -> String {
+    DateTime::<Utc>::from_timestamp(unix_secs as i64, 0)
+        .expect("unix timestamp in range")
+        .to_rfc3339_opts(SecondsFormat::Secs, true)
+}
@@ -0,0 +1,82 @@
+//! Structured logging setup.
+//!
+//! Replaces ad-hoc `println!` calls with `tracing` spans and events so logs
+//! are timestamped and filterable. Only `POST /verify` runs inside a span
+//! carrying the request's `X-Correlation-ID`; the other routes are not
+//! correlation-tagged. Output is configured entirely through environment variables so handlers
+//! never need to know where a log line ends up:
+//!
+//! - `RUST_LOG` selects verbosity (default `info`), same as any `tracing`
+//!   service.
+//! - `LOG_FORMAT=json` switches from human-readable to JSON output.
+//! - `LOG_FILE`, when set, additionally appends logs to that path.
+//! - The `syslog` Cargo feature, when enabled, adds a syslog sink alongside
+//!   stdout/file output.
+
+use std::fs::OpenOptions;
+
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer, Registry};
+
+/// Installs the global `tracing` subscriber. Must be called once, before
+/// any spans or events are recorded (i.e. first thing in `main`).
+pub fn init() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let json = std::env::var("LOG_FORMAT").as_deref() == Ok("json");
+
+    let stdout_layer = fmt_layer(json, std::io::stdout);
+
+    let file_layer = std::env::var("LOG_FILE").ok().and_then(|path| {
+        match OpenOptions::new().create(true).append(true).open(&path) {
+            Ok(file) => Some(fmt_layer(json, move || file.try_clone().expect("clone log file handle"))),
+            Err(e) => {
+                eprintln!("Failed to open LOG_FILE {}: {}", path, e);
+                None
+            }
+        }
+    });
+
+    let registry = Registry::default()
+        .with(filter)
+        .with(stdout_layer)
+        .with(file_layer);
+
+    #[cfg(feature = "syslog")]
+    let registry = registry.with(syslog_layer());
+
+    registry.init();
+}
+
+fn fmt_layer<W>(
+    json: bool,
+    writer: W,
+) -> Box<dyn Layer<Registry> + Send + Sync + 'static>
+where
+    W: for<'a> tracing_subscriber::fmt::MakeWriter<'a> + Send + Sync + 'static,
+{
+    if json {
+        tracing_subscriber::fmt::layer()
+            .json()
+            .with_writer(writer)
+            .boxed()
+    } else {
+        tracing_subscriber::fmt::layer().with_writer(writer).boxed()
+    }
+}
+
+/// Syslog sink, only compiled in when the `syslog` feature is enabled.
+#[cfg(feature = "syslog")]
+fn syslog_layer() -> Box<dyn Layer<Registry> + Send + Sync + 'static> {
+    let formatter = syslog_tracing::Syslog::new(
+        "microai-paygate",
+        syslog_tracing::Facility::Daemon,
+        syslog_tracing::Options::LOG_PID,
+    )
+    .expect("failed to connect to syslog");
+
+    tracing_subscriber::fmt::layer()
+        .with_writer(formatter)
+        .with_ansi(false)
+        .boxed()
+}
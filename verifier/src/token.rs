@@ -0,0 +1,149 @@
+//! Short-lived JWT payment tokens.
+//!
+//! After a payment authorization is verified, a signed token lets
+//! downstream services trust that verification happened without
+//! re-checking the EIP-712 signature themselves.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+
+/// Claims carried by a payment token.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentClaims {
+    /// Recovered signer address.
+    pub sub: String,
+    pub recipient: String,
+    pub token: String,
+    pub amount: String,
+    pub nonce: String,
+    #[serde(rename = "chainId")]
+    pub chain_id: u64,
+    pub iat: u64,
+    pub exp: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,
+}
+
+/// Why a token failed to decode or validate.
+#[derive(Debug)]
+pub enum TokenError {
+    Expired,
+    NotYetValid,
+    WrongAudience,
+    Invalid(String),
+}
+
+impl std::fmt::Display for TokenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenError::Expired => write!(f, "token has expired"),
+            TokenError::NotYetValid => write!(f, "token is not yet valid"),
+            TokenError::WrongAudience => write!(f, "token audience does not match"),
+            TokenError::Invalid(msg) => write!(f, "invalid token: {}", msg),
+        }
+    }
+}
+
+impl From<jsonwebtoken::errors::Error> for TokenError {
+    fn from(err: jsonwebtoken::errors::Error) -> Self {
+        use jsonwebtoken::errors::ErrorKind;
+        match err.kind() {
+            ErrorKind::ExpiredSignature => TokenError::Expired,
+            ErrorKind::ImmatureSignature => TokenError::NotYetValid,
+            ErrorKind::InvalidAudience => TokenError::WrongAudience,
+            _ => TokenError::Invalid(err.to_string()),
+        }
+    }
+}
+
+/// Issues and validates HS256 payment tokens signed with a server secret.
+pub struct TokenIssuer {
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    algorithm: Algorithm,
+    ttl_seconds: u64,
+    /// Leeway (seconds) applied to `exp`/`nbf` checks at decode time.
+    leeway_seconds: u64,
+    audience: Option<String>,
+}
+
+impl TokenIssuer {
+    /// Builds an issuer from `JWT_SECRET` (HS256 signing secret),
+    /// `JWT_TTL_SECONDS` (default 300), `JWT_LEEWAY_SECONDS` (default 30),
+    /// and an optional `JWT_AUDIENCE`.
+    pub fn from_env() -> Self {
+        let secret =
+            std::env::var("JWT_SECRET").unwrap_or_else(|_| "dev-secret-change-me".to_string());
+        let ttl_seconds = std::env::var("JWT_TTL_SECONDS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(300);
+        let leeway_seconds = std::env::var("JWT_LEEWAY_SECONDS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(30);
+        let audience = std::env::var("JWT_AUDIENCE").ok();
+
+        Self {
+            encoding_key: EncodingKey::from_secret(secret.as_bytes()),
+            decoding_key: DecodingKey::from_secret(secret.as_bytes()),
+            algorithm: Algorithm::HS256,
+            ttl_seconds,
+            leeway_seconds,
+            audience,
+        }
+    }
+
+    /// Mints a token attesting that `sub` authorized the given payment
+    /// context.
+    pub fn issue(
+        &self,
+        sub: &str,
+        recipient: &str,
+        token: &str,
+        amount: &str,
+        nonce: &str,
+        chain_id: u64,
+    ) -> Result<String, TokenError> {
+        let now = now_unix();
+        let claims = PaymentClaims {
+            sub: sub.to_string(),
+            recipient: recipient.to_string(),
+            token: token.to_string(),
+            amount: amount.to_string(),
+            nonce: nonce.to_string(),
+            chain_id,
+            iat: now,
+            exp: now + self.ttl_seconds,
+            aud: self.audience.clone(),
+        };
+
+        encode(&Header::new(self.algorithm), &claims, &self.encoding_key)
+            .map_err(|e| TokenError::Invalid(e.to_string()))
+    }
+
+    /// Decodes `token`, rejecting expired, not-yet-valid, or wrong-audience
+    /// tokens with a distinct [`TokenError`] variant.
+    pub fn decode_and_validate(&self, token: &str) -> Result<PaymentClaims, TokenError> {
+        let mut validation = Validation::new(self.algorithm);
+        validation.leeway = self.leeway_seconds;
+        validation.validate_exp = true;
+        validation.validate_nbf = true;
+        match &self.audience {
+            Some(aud) => validation.set_audience(&[aud]),
+            None => validation.validate_aud = false,
+        }
+
+        let data = decode::<PaymentClaims>(token, &self.decoding_key, &validation)?;
+        Ok(data.claims)
+    }
+}
+
+fn now_unix() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system clock is before the unix epoch")
+        .as_secs()
+}